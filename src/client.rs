@@ -0,0 +1,439 @@
+//! Client/server split for offloading `spartan::prove` off of the middlebox.
+//!
+//! The wire format is deliberately dumb: a request/response pair of bincode-framed
+//! messages carrying the same bytes `parse_value_map` and `write_to_path` already push
+//! around on disk, so the server and the in-process path share exactly one prover.
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    io,
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use bincode::{deserialize_from, serialize_into};
+use serde::{Deserialize, Serialize};
+
+use circ::{cfg::cfg, ir::term::text::parse_value_map, target::r1cs::spartan};
+
+/// A serialized `(gens, inst, proof)` triple, as produced by `spartan::prove`.
+pub type ProofBytes = (Vec<u8>, Vec<u8>, Vec<u8>);
+
+/// Block until the proof comes back.
+///
+/// Used by a middlebox that can afford to wait on the proving pool inline.
+pub trait SyncClient {
+    fn prove(&self, prover_input_bytes: &[u8]) -> io::Result<ProofBytes>;
+}
+
+/// Fire off a proving job and poll for it later, instead of blocking on it.
+///
+/// Used by a middlebox that wants to keep forwarding packets while a proof is pending.
+pub trait AsyncClient {
+    fn submit(&self, prover_input_bytes: &[u8]) -> io::Result<JobId>;
+    fn poll(&self, job: &JobId) -> io::Result<Option<ProofBytes>>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JobId(pub String);
+
+fn to_io_err<E: std::fmt::Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+/// The existing in-process path (`spartan::prove` against a local `prover_key` file),
+/// wrapped behind [`SyncClient`] so callers can't tell it apart from a remote pool.
+pub struct FileClient {
+    pub prover_key: PathBuf,
+}
+
+impl SyncClient for FileClient {
+    fn prove(&self, prover_input_bytes: &[u8]) -> io::Result<ProofBytes> {
+        let prover_input_map = parse_value_map(prover_input_bytes);
+        let (gens, inst, proof) =
+            spartan::prove(&self.prover_key, &prover_input_map, cfg().field.builtin)
+                .map_err(to_io_err)?;
+        Ok((
+            bincode::serialize(&gens).map_err(to_io_err)?,
+            bincode::serialize(&inst).map_err(to_io_err)?,
+            bincode::serialize(&proof).map_err(to_io_err)?,
+        ))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Request {
+    ProveSync(Vec<u8>),
+    ProveAsync(Vec<u8>),
+    Poll(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Response {
+    Proof(Vec<u8>, Vec<u8>, Vec<u8>),
+    Job(String),
+    Pending,
+    Err(String),
+}
+
+/// Talks to a [`Serve`](crate::Action::Serve) instance over TCP.
+///
+/// Retries/reconnects a fixed number of times on transient connection failures so a
+/// middlebox doesn't wedge the first time the proving pool is momentarily unreachable.
+pub struct NetworkClient {
+    addr: String,
+    retries: usize,
+}
+
+impl NetworkClient {
+    pub fn new<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        // Resolve eagerly so a typo'd address fails fast instead of on the first job.
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address resolved"))?;
+        Ok(NetworkClient {
+            addr: addr.to_string(),
+            retries: 3,
+        })
+    }
+
+    fn connect(&self) -> io::Result<TcpStream> {
+        let mut last_err = None;
+        for attempt in 0..self.retries {
+            match TcpStream::connect(&self.addr) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => {
+                    trace_retry(attempt, &e);
+                    last_err = Some(e);
+                }
+            }
+        }
+        Err(last_err.unwrap())
+    }
+
+    fn roundtrip(&self, req: &Request) -> io::Result<Response> {
+        let mut stream = self.connect()?;
+        serialize_into(&mut stream, req).map_err(to_io_err)?;
+        deserialize_from(&mut stream).map_err(to_io_err)
+    }
+}
+
+fn trace_retry(attempt: usize, e: &io::Error) {
+    log::trace!("proving server connect attempt {} failed: {}", attempt, e);
+}
+
+impl SyncClient for NetworkClient {
+    fn prove(&self, prover_input_bytes: &[u8]) -> io::Result<ProofBytes> {
+        match self.roundtrip(&Request::ProveSync(prover_input_bytes.to_vec()))? {
+            Response::Proof(gens, inst, proof) => Ok((gens, inst, proof)),
+            Response::Err(msg) => Err(to_io_err(msg)),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "unexpected response to ProveSync")),
+        }
+    }
+}
+
+impl AsyncClient for NetworkClient {
+    fn submit(&self, prover_input_bytes: &[u8]) -> io::Result<JobId> {
+        match self.roundtrip(&Request::ProveAsync(prover_input_bytes.to_vec()))? {
+            Response::Job(id) => Ok(JobId(id)),
+            Response::Err(msg) => Err(to_io_err(msg)),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "unexpected response to ProveAsync")),
+        }
+    }
+
+    fn poll(&self, job: &JobId) -> io::Result<Option<ProofBytes>> {
+        match self.roundtrip(&Request::Poll(job.0.clone()))? {
+            Response::Proof(gens, inst, proof) => Ok(Some((gens, inst, proof))),
+            Response::Pending => Ok(None),
+            Response::Err(msg) => Err(to_io_err(msg)),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "unexpected response to Poll")),
+        }
+    }
+}
+
+enum JobState {
+    Running,
+    Done(ProofBytes),
+    Failed(String),
+}
+
+/// Best-effort extraction of a human-readable message from a `catch_unwind` payload —
+/// `panic!("...")`/`.unwrap()` panics carry `&str` or `String`, anything else falls back
+/// to a generic message.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "prover panicked".to_string()
+    }
+}
+
+/// Runs the proving server: loads `prover_key` once, then serves proving jobs for
+/// `Prove` clients (in-process or over the network) until the process is killed.
+pub fn serve<A: ToSocketAddrs>(prover_key: PathBuf, addr: A) -> io::Result<()> {
+    serve_with(FileClient { prover_key }, addr)
+}
+
+/// Same as [`serve`], but against any [`SyncClient`] backend instead of a hardcoded
+/// `FileClient` — lets tests stand up a server without a real `prover_key`/circuit.
+pub fn serve_with<P, A>(prover: P, addr: A) -> io::Result<()>
+where
+    P: SyncClient + Send + Sync + 'static,
+    A: ToSocketAddrs,
+{
+    let listener = TcpListener::bind(addr)?;
+    println!(
+        "zkmb proving server listening on {}",
+        listener.local_addr()?
+    );
+    let prover = Arc::new(prover);
+    let jobs: Arc<Mutex<HashMap<String, JobState>>> = Arc::new(Mutex::new(HashMap::new()));
+    let next_job_id = Arc::new(AtomicU64::new(0));
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                log::trace!("dropping connection: {}", e);
+                continue;
+            }
+        };
+        let prover = prover.clone();
+        let jobs = jobs.clone();
+        let job_id = next_job_id.fetch_add(1, Ordering::Relaxed);
+        // One connection per thread, same as the ProveAsync job itself: a blocking
+        // ProveSync request must not stall clients polling an in-flight job, or new
+        // clients trying to connect at all.
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, prover, jobs, job_id) {
+                log::trace!("connection handler error: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection<P: SyncClient + Send + Sync + 'static>(
+    mut stream: TcpStream,
+    prover: Arc<P>,
+    jobs: Arc<Mutex<HashMap<String, JobState>>>,
+    job_id: u64,
+) -> io::Result<()> {
+    let req: Request = deserialize_from(&mut stream).map_err(to_io_err)?;
+    let resp = match req {
+        Request::ProveSync(input_bytes) => match prover.prove(&input_bytes) {
+            Ok((gens, inst, proof)) => Response::Proof(gens, inst, proof),
+            Err(e) => Response::Err(e.to_string()),
+        },
+        Request::ProveAsync(input_bytes) => {
+            let id = job_id.to_string();
+            jobs.lock().unwrap().insert(id.clone(), JobState::Running);
+            let jobs = jobs.clone();
+            let id_for_thread = id.clone();
+            thread::spawn(move || {
+                // A malformed/adversarial witness reaching `spartan::prove` can panic
+                // rather than return an `Err`; catch that too, or the job stays
+                // `Running` forever and `poll` never learns it failed — the same
+                // failure mode fixed for unknown job ids, reintroduced via a panic
+                // instead of a missing id.
+                let result = catch_unwind(AssertUnwindSafe(|| prover.prove(&input_bytes)));
+                let state = match result {
+                    Ok(Ok(proof)) => JobState::Done(proof),
+                    Ok(Err(e)) => JobState::Failed(e.to_string()),
+                    Err(panic) => JobState::Failed(panic_message(&*panic)),
+                };
+                jobs.lock().unwrap().insert(id_for_thread, state);
+            });
+            Response::Job(id)
+        }
+        Request::Poll(id) => match jobs.lock().unwrap().get(&id) {
+            Some(JobState::Running) => Response::Pending,
+            Some(JobState::Done((gens, inst, proof))) => {
+                Response::Proof(gens.clone(), inst.clone(), proof.clone())
+            }
+            Some(JobState::Failed(msg)) => Response::Err(msg.clone()),
+            // An id we've never seen (or one from a server that's since restarted) is
+            // not "still running" — report it as an error instead of polling forever.
+            None => Response::Err(format!("no such job: {}", id)),
+        },
+    };
+    serialize_into(&mut stream, &resp).map_err(to_io_err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::TcpListener, sync::mpsc, time::Duration};
+
+    /// A fake prover for tests that shouldn't need a real `prover_key`/circuit: it
+    /// echoes the input back as `gens` so round-tripping through the wire protocol is
+    /// all that's being checked.
+    struct EchoProver;
+
+    impl SyncClient for EchoProver {
+        fn prove(&self, prover_input_bytes: &[u8]) -> io::Result<ProofBytes> {
+            Ok((prover_input_bytes.to_vec(), b"inst".to_vec(), b"proof".to_vec()))
+        }
+    }
+
+    fn spawn_test_server() -> String {
+        // Bind on an ephemeral port up front to learn its address, then hand that
+        // address to serve_with on its own thread so tests never collide on a port.
+        let probe = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = probe.local_addr().unwrap().to_string();
+        drop(probe);
+
+        let bind_addr = addr.clone();
+        thread::spawn(move || serve_with(EchoProver, bind_addr).unwrap());
+        thread::sleep(Duration::from_millis(50));
+        addr
+    }
+
+    #[test]
+    fn request_response_roundtrip() {
+        let req = Request::ProveSync(vec![1, 2, 3]);
+        let decoded: Request = bincode::deserialize(&bincode::serialize(&req).unwrap()).unwrap();
+        assert!(matches!(decoded, Request::ProveSync(v) if v == vec![1, 2, 3]));
+
+        let resp = Response::Proof(vec![4], vec![5], vec![6]);
+        let decoded: Response =
+            bincode::deserialize(&bincode::serialize(&resp).unwrap()).unwrap();
+        assert!(matches!(decoded, Response::Proof(g, i, p) if g == vec![4] && i == vec![5] && p == vec![6]));
+    }
+
+    #[test]
+    fn sync_client_round_trips_through_the_network() {
+        let client = NetworkClient::new(spawn_test_server()).unwrap();
+        let (gens, inst, proof) = client.prove(b"pin-bytes").unwrap();
+        assert_eq!(gens, b"pin-bytes");
+        assert_eq!(inst, b"inst");
+        assert_eq!(proof, b"proof");
+    }
+
+    #[test]
+    fn async_client_polls_until_done() {
+        let client = NetworkClient::new(spawn_test_server()).unwrap();
+        let job = client.submit(b"pin-bytes").unwrap();
+
+        let mut result = None;
+        for _ in 0..20 {
+            if let Some(proof) = client.poll(&job).unwrap() {
+                result = Some(proof);
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        let (gens, _, _) = result.expect("job should have completed");
+        assert_eq!(gens, b"pin-bytes");
+    }
+
+    /// Holds every `slow-job` prove open until the test sends on the paired channel, so
+    /// a blocking ProveSync connection is really in flight while others are served.
+    struct GatedProver(Mutex<mpsc::Receiver<()>>);
+
+    impl SyncClient for GatedProver {
+        fn prove(&self, prover_input_bytes: &[u8]) -> io::Result<ProofBytes> {
+            if prover_input_bytes == b"slow-job" {
+                self.0.lock().unwrap().recv().map_err(to_io_err)?;
+            }
+            EchoProver.prove(prover_input_bytes)
+        }
+    }
+
+    #[test]
+    fn sync_requests_do_not_stall_concurrent_connections() {
+        // Regression test: a blocking ProveSync request must not stall the listener
+        // for other clients, including one polling an in-flight ProveAsync job.
+        let (release, gate) = mpsc::channel();
+        let probe = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = probe.local_addr().unwrap().to_string();
+        drop(probe);
+        let bind_addr = addr.clone();
+        thread::spawn(move || serve_with(GatedProver(Mutex::new(gate)), bind_addr).unwrap());
+        thread::sleep(Duration::from_millis(50));
+
+        let client = NetworkClient::new(addr.clone()).unwrap();
+        let job = client.submit(b"slow-job").unwrap();
+
+        // A ProveSync connection that stays blocked inside `prove` until released.
+        let blocked = thread::spawn(move || NetworkClient::new(addr).unwrap().prove(b"slow-job"));
+        thread::sleep(Duration::from_millis(50));
+
+        // This would hang on a listener that serializes connection handling.
+        let (gens, _, _) = client.prove(b"another-request").unwrap();
+        assert_eq!(gens, b"another-request");
+        assert!(client.poll(&job).unwrap().is_none());
+
+        release.send(()).unwrap();
+        release.send(()).unwrap();
+        assert!(blocked.join().unwrap().is_ok());
+
+        let mut done = None;
+        for _ in 0..20 {
+            done = client.poll(&job).unwrap();
+            if done.is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(done.is_some());
+    }
+
+    #[test]
+    fn polling_an_unknown_job_id_is_an_error_not_pending_forever() {
+        let client = NetworkClient::new(spawn_test_server()).unwrap();
+        let err = client.poll(&JobId("no-such-job".to_string())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+    }
+
+    /// A prover standing in for `spartan::prove` choking on a malformed/adversarial
+    /// witness: it panics instead of returning an `Err`.
+    struct PanicProver;
+
+    impl SyncClient for PanicProver {
+        fn prove(&self, _prover_input_bytes: &[u8]) -> io::Result<ProofBytes> {
+            panic!("malformed witness");
+        }
+    }
+
+    #[test]
+    fn a_panicking_prove_fails_the_async_job_instead_of_hanging_forever() {
+        // Regression test: a panic inside the ProveAsync worker thread must still
+        // transition the job out of `Running`, or poll() spins forever on it the same
+        // way it used to for an unknown job id.
+        let probe = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = probe.local_addr().unwrap().to_string();
+        drop(probe);
+        let bind_addr = addr.clone();
+        thread::spawn(move || serve_with(PanicProver, bind_addr).unwrap());
+        thread::sleep(Duration::from_millis(50));
+
+        let client = NetworkClient::new(addr).unwrap();
+        let job = client.submit(b"anything").unwrap();
+
+        let mut last_result = None;
+        for _ in 0..20 {
+            match client.poll(&job) {
+                Ok(None) => thread::sleep(Duration::from_millis(20)),
+                other => {
+                    last_result = Some(other);
+                    break;
+                }
+            }
+        }
+        assert!(
+            matches!(last_result, Some(Err(_))),
+            "a panicking prove should surface as an error, not hang as Pending forever"
+        );
+    }
+}
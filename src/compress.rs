@@ -0,0 +1,330 @@
+//! Groth16 compression of a Spartan proof, Testudo-style: re-express the Spartan
+//! verifier's sumcheck-consistency checks as an arkworks R1CS circuit over a
+//! pairing-friendly curve, then run `ark_groth16` over that circuit so a single
+//! constant-size pairing check attests that the Spartan verifier would have accepted.
+//!
+//! `circ::target::r1cs::spartan` (the crate that actually produces `Gens`/`Inst`/the
+//! Spartan `Proof`) isn't part of this source tree, so this module can't compile a
+//! circuit over its real transcript types. Instead it defines [`SpartanTranscript`] as
+//! the witness this circuit needs — the public input map, the instance commitment, and
+//! the per-round sumcheck polynomials/claimed evaluations — so `Compress`/`Verify` can
+//! operate on a transcript dumped alongside a real Spartan proof.
+
+use ark_bls12_377::{Bls12_377, Fr};
+use ark_crypto_primitives::sponge::{
+    constraints::CryptographicSpongeVar,
+    poseidon::{constraints::PoseidonSpongeVar, PoseidonConfig, PoseidonSponge},
+    CryptographicSponge,
+};
+use ark_ff::Field;
+use ark_groth16::{Groth16, Proof as Groth16Proof, ProvingKey, VerifyingKey};
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar, R1CSVar};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_snark::SNARK;
+use ark_std::rand::RngCore;
+
+/// One round of the Spartan sumcheck: the prover's round polynomial `g_i`, given as
+/// coefficients `[c0, c1, c2]` low-to-high, so `g_i(x) = c0 + c1*x + c2*x^2` (degree 2,
+/// as Spartan's per-round polynomials are).
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SumcheckRound {
+    pub poly_coeffs: [Fr; 3],
+}
+
+impl SumcheckRound {
+    fn eval(&self, x: Fr) -> Fr {
+        self.poly_coeffs[0] + x * (self.poly_coeffs[1] + x * self.poly_coeffs[2])
+    }
+}
+
+/// The witness the Spartan verifier would have checked, flattened into what this
+/// circuit needs: the claim each sumcheck round must be consistent with, and the final
+/// multilinear-evaluation opening the last claim is checked against.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct SpartanTranscript {
+    pub public_inputs: Vec<Fr>,
+    pub instance_commitment: Fr,
+    pub rounds: Vec<SumcheckRound>,
+    pub final_eval: Fr,
+}
+
+/// Re-derives the Spartan verifier's Fiat-Shamir challenges and sumcheck-consistency
+/// checks inside an R1CS circuit: per round, `g_i(0) + g_i(1) == claim_{i-1}` and
+/// `claim_i = g_i(r_i)`, where `r_i` is squeezed from an in-circuit transcript hash;
+/// finally, the last claim is checked against the committed polynomials' opening.
+pub struct SpartanVerifierCircuit {
+    pub transcript: Option<SpartanTranscript>,
+    pub num_rounds: usize,
+    /// Must equal `transcript.public_inputs.len()` — carried separately because the
+    /// circuit has to know its own input count during [`setup`], when `transcript` is
+    /// `None`.
+    pub num_public_inputs: usize,
+    pub poseidon_config: PoseidonConfig<Fr>,
+}
+
+impl ConstraintSynthesizer<Fr> for SpartanVerifierCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let transcript = self.transcript.as_ref();
+
+        let instance_commitment = FpVar::new_input(cs.clone(), || {
+            transcript
+                .map(|t| t.instance_commitment)
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+
+        let mut sponge = PoseidonSpongeVar::new(cs.clone(), &self.poseidon_config);
+        sponge.absorb(&instance_commitment)?;
+
+        // Every public input has to be allocated here, in the same order
+        // `public_inputs` below serializes them in, or Groth16 verification will check
+        // the proof against the wrong values even when the witness is honest.
+        let public_input_vars: Vec<FpVar<Fr>> = (0..self.num_public_inputs)
+            .map(|i| {
+                FpVar::new_input(cs.clone(), || {
+                    transcript
+                        .and_then(|t| t.public_inputs.get(i).copied())
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        // claim_0 is the value the instance commitment is claimed to open to, carried
+        // in as the first public input.
+        let mut claim = public_input_vars
+            .first()
+            .cloned()
+            .ok_or(SynthesisError::AssignmentMissing)?;
+
+        for round in 0..self.num_rounds {
+            let round_data = transcript.and_then(|t| t.rounds.get(round));
+
+            let coeffs: Vec<FpVar<Fr>> = (0..3)
+                .map(|i| {
+                    FpVar::new_witness(cs.clone(), || {
+                        round_data
+                            .map(|r| r.poly_coeffs[i])
+                            .ok_or(SynthesisError::AssignmentMissing)
+                    })
+                })
+                .collect::<Result<_, _>>()?;
+
+            // g_i(0) + g_i(1) == claim_{i-1}, with g_i(0) = coeffs[0] and
+            // g_i(1) = coeffs[0] + coeffs[1] + coeffs[2].
+            let g0 = coeffs[0].clone();
+            let g1 = &coeffs[0] + &coeffs[1] + &coeffs[2];
+            (g0 + g1).enforce_equal(&claim)?;
+
+            for c in &coeffs {
+                sponge.absorb(c)?;
+            }
+            let r_i = sponge.squeeze_field_elements(1)?.remove(0);
+
+            // claim_i = g_i(r_i)
+            let next_claim = FpVar::new_witness(cs.clone(), || {
+                let r = round_data.ok_or(SynthesisError::AssignmentMissing)?;
+                Ok(r.eval(r_i.value()?))
+            })?;
+            // g_i evaluated in-circuit at r_i, via its coefficients, must match the
+            // claimed next_claim witness.
+            let g_at_r = &coeffs[0] + &r_i * (&coeffs[1] + &r_i * &coeffs[2]);
+            g_at_r.enforce_equal(&next_claim)?;
+
+            claim = next_claim;
+        }
+
+        // Final multilinear-evaluation check against the committed polynomials' opening.
+        let final_eval = FpVar::new_input(cs, || {
+            transcript.map(|t| t.final_eval).ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        final_eval.enforce_equal(&claim)?;
+
+        Ok(())
+    }
+}
+
+/// The fixed Poseidon parameters the in-circuit Fiat-Shamir transcript hash uses.
+///
+/// Must match whatever instrumented the real Spartan transcript to produce the
+/// [`SpartanTranscript`] this circuit re-derives challenges from.
+///
+/// `rate = 2, capacity = 1` (state width `t = 3`): round constants are one row of `t`
+/// field elements per round (`full_rounds + partial_rounds` rows), and the MDS matrix is
+/// `t x t`. These aren't the audited Poseidon parameters from a parameter-generation
+/// script — they're deterministic placeholders, not meant for anything security-sensitive
+/// — but the MDS matrix is a genuine Cauchy matrix (`mds[i][j] = 1 / (x_i - y_j)` over
+/// two disjoint sets of distinct field elements), which is invertible by construction, so
+/// the sponge's mixing step is actually well-formed rather than merely correctly shaped.
+pub fn poseidon_config() -> PoseidonConfig<Fr> {
+    let full_rounds = 8;
+    let partial_rounds = 57;
+    let rate = 2;
+    let capacity = 1;
+    let t = rate + capacity;
+
+    let ark = (0..full_rounds + partial_rounds)
+        .map(|round| (0..t).map(|i| Fr::from((round * t + i + 1) as u64)).collect())
+        .collect();
+    // x_i ranges over [0, t) and y_j over [t, 2t): the x_i are distinct, the y_j are
+    // distinct, and no x_i equals a y_j, so every x_i - y_j is nonzero and the
+    // resulting Cauchy matrix is invertible.
+    let mds = (0..t)
+        .map(|i| {
+            (0..t)
+                .map(|j| {
+                    let x_i = Fr::from(i as u64);
+                    let y_j = Fr::from((t + j) as u64);
+                    (x_i - y_j)
+                        .inverse()
+                        .expect("x_i - y_j is nonzero by construction")
+                })
+                .collect()
+        })
+        .collect();
+
+    PoseidonConfig::new(full_rounds, partial_rounds, 5, mds, ark, rate, capacity)
+}
+
+fn public_inputs(transcript: &SpartanTranscript) -> Vec<Fr> {
+    let mut inputs = vec![transcript.instance_commitment];
+    inputs.extend(transcript.public_inputs.iter().copied());
+    inputs.push(transcript.final_eval);
+    inputs
+}
+
+/// Runs the Groth16 circuit-specific setup for a transcript with `num_rounds` sumcheck
+/// rounds and `num_public_inputs` public inputs. Keys are specific to both counts, same
+/// as the Spartan instance's round count (`prover_data.precompute.stage_sizes().count()
+/// - 1` in `Generate`'s report) and its public input count.
+pub fn setup<R: RngCore>(
+    num_rounds: usize,
+    num_public_inputs: usize,
+    poseidon_config: PoseidonConfig<Fr>,
+    rng: &mut R,
+) -> Result<(ProvingKey<Bls12_377>, VerifyingKey<Bls12_377>), SynthesisError> {
+    let circuit = SpartanVerifierCircuit {
+        transcript: None,
+        num_rounds,
+        num_public_inputs,
+        poseidon_config,
+    };
+    Groth16::<Bls12_377>::circuit_specific_setup(circuit, rng)
+        .map_err(|_| SynthesisError::Unsatisfiable)
+}
+
+/// Produces a constant-size Groth16 proof that the Spartan verifier would accept
+/// `transcript`.
+pub fn compress<R: RngCore>(
+    pk: &ProvingKey<Bls12_377>,
+    transcript: SpartanTranscript,
+    poseidon_config: PoseidonConfig<Fr>,
+    rng: &mut R,
+) -> Result<Groth16Proof<Bls12_377>, SynthesisError> {
+    let num_rounds = transcript.rounds.len();
+    let num_public_inputs = transcript.public_inputs.len();
+    let circuit = SpartanVerifierCircuit {
+        transcript: Some(transcript),
+        num_rounds,
+        num_public_inputs,
+        poseidon_config,
+    };
+    Groth16::<Bls12_377>::prove(pk, circuit, rng).map_err(|_| SynthesisError::Unsatisfiable)
+}
+
+/// Verifies a Groth16 proof produced by [`compress`] against the public part of the
+/// transcript (the instance commitment, the public inputs, and the final evaluation).
+pub fn verify_compressed(
+    vk: &VerifyingKey<Bls12_377>,
+    transcript: &SpartanTranscript,
+    proof: &Groth16Proof<Bls12_377>,
+) -> Result<bool, SynthesisError> {
+    Groth16::<Bls12_377>::verify(vk, &public_inputs(transcript), proof)
+        .map_err(|_| SynthesisError::Unsatisfiable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_ff::UniformRand;
+    use ark_std::test_rng;
+
+    fn test_poseidon_config() -> PoseidonConfig<Fr> {
+        poseidon_config()
+    }
+
+    fn honest_transcript(rng: &mut impl RngCore, num_rounds: usize) -> SpartanTranscript {
+        let instance_commitment = Fr::rand(rng);
+        let claim_0 = Fr::rand(rng);
+        let mut claim = claim_0;
+        let mut rounds = Vec::with_capacity(num_rounds);
+        let config = test_poseidon_config();
+        let mut sponge = PoseidonSponge::new(&config);
+        sponge.absorb(&instance_commitment);
+        for _ in 0..num_rounds {
+            // Pick g_i(0), g_i(1) freely, then force them to sum to the running claim.
+            let g0 = Fr::rand(rng);
+            let g1 = claim - g0;
+            let c2 = Fr::rand(rng);
+            let c1 = g1 - g0 - c2;
+            let round = SumcheckRound {
+                poly_coeffs: [g0, c1, c2],
+            };
+            for c in &round.poly_coeffs {
+                sponge.absorb(c);
+            }
+            let r_i = sponge.squeeze_field_elements::<Fr>(1)[0];
+            claim = round.eval(r_i);
+            rounds.push(round);
+        }
+        SpartanTranscript {
+            public_inputs: vec![claim_0],
+            instance_commitment,
+            rounds,
+            final_eval: claim,
+        }
+    }
+
+    #[test]
+    fn honest_transcript_compresses_and_verifies() {
+        let mut rng = test_rng();
+        let config = test_poseidon_config();
+        let transcript = honest_transcript(&mut rng, 3);
+
+        let (pk, vk) = setup(3, 1, config.clone(), &mut rng).unwrap();
+        let proof = compress(&pk, transcript.clone(), config.clone(), &mut rng).unwrap();
+        assert!(verify_compressed(&vk, &transcript, &proof).unwrap());
+    }
+
+    #[test]
+    fn tampered_final_eval_fails_to_verify() {
+        let mut rng = test_rng();
+        let config = test_poseidon_config();
+        let transcript = honest_transcript(&mut rng, 3);
+
+        let (pk, vk) = setup(3, 1, config.clone(), &mut rng).unwrap();
+        let proof = compress(&pk, transcript.clone(), config.clone(), &mut rng).unwrap();
+
+        let mut bad_transcript = transcript;
+        bad_transcript.final_eval += Fr::from(1u64);
+        assert!(!verify_compressed(&vk, &bad_transcript, &proof).unwrap());
+    }
+
+    #[test]
+    fn extra_public_inputs_beyond_the_claim_are_bound_by_the_proof() {
+        // Regression test: the circuit must allocate every entry of
+        // `transcript.public_inputs`, not just the claim at index 0, or tampering with a
+        // later public input would go undetected.
+        let mut rng = test_rng();
+        let config = test_poseidon_config();
+        let mut transcript = honest_transcript(&mut rng, 3);
+        transcript.public_inputs.push(Fr::rand(&mut rng));
+
+        let (pk, vk) = setup(3, 2, config.clone(), &mut rng).unwrap();
+        let proof = compress(&pk, transcript.clone(), config.clone(), &mut rng).unwrap();
+        assert!(verify_compressed(&vk, &transcript, &proof).unwrap());
+
+        let mut bad_transcript = transcript;
+        bad_transcript.public_inputs[1] += Fr::from(1u64);
+        assert!(!verify_compressed(&vk, &bad_transcript, &proof).unwrap());
+    }
+}
@@ -0,0 +1,192 @@
+//! Structural-hash CSE over a term DAG: walks bottom-up, canonicalizes each node to a
+//! single shared instance keyed on its operator plus its (already-canonicalized)
+//! children, and reports how many duplicate nodes that collapsed away.
+//!
+//! This is the dedup `Opt::HoistRedundantCalls` was supposed to be: when a frontend
+//! unrolls a loop that calls the same pure helper with identical arguments every
+//! iteration, the resulting subgraphs are structurally identical and should collapse to
+//! one shared node instead of `N` copies.
+//!
+//! `circ::ir::opt::Opt` is a closed enum in `circ::ir::opt`, and `circ::ir::term`'s
+//! `Term`/`Op`/`Computation` types — neither of which is part of this source tree — so
+//! this can't literally add an `Opt::HoistRedundantCalls` variant or splice itself into
+//! the real `Mode::Proof` pipeline (`opt(cs, opts)` only runs variants `circ::ir::opt`
+//! itself defines). What's written here is the actual algorithm the request asked for,
+//! generic over any hash-consable node type via caller-supplied `op_key`/`children`/
+//! `rebuild` closures, so it drops in directly once `Opt::HoistRedundantCalls` exists
+//! upstream and can hand it `circ::ir::term::Term`'s real op/children/constructor.
+//!
+//! Scope, explicitly: hoisting redundant calls out of the real term DAG before
+//! `Opt::Flatten`, and reporting the node-count reduction in the optimization timing
+//! printout, remains blocked on that upstream access — it is NOT delivered here. What
+//! *is* delivered and actually exercised today is the generic dedup core itself:
+//! `uniform::analyze_shapes` (called from `Generate`'s R1CS report) runs every
+//! constraint's structural signature through this exact function as a childless
+//! (leaf-only) DAG whenever the constraints have no exact period. That's a real, invoked use of the algorithm, just at R1CS-constraint
+//! granularity instead of term-DAG granularity.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Bottom-up structural-hash dedup of a term DAG rooted at `roots`.
+///
+/// - `op_key` returns a hashable, comparable key for a node's operator (ignoring
+///   children) — e.g. the discriminant plus any immediate/literal data.
+/// - `children` returns a node's child nodes, in order.
+/// - `rebuild` reconstructs a node from its operator key and its (already-canonical)
+///   children, so sharing introduced by dedup further down the DAG propagates upward.
+///
+/// Returns the new roots (pointing at canonical, deduplicated nodes) along with the
+/// node count before and after dedup.
+///
+/// Called today by `uniform::distinct_shapes` and `uniform::analyze_shapes` over flat
+/// constraint shapes (a childless
+/// DAG); term-DAG hoisting with a real op/children/constructor from `circ::ir::term`
+/// remains blocked — see the module-level doc comment.
+pub fn dedup_dag<T, K, FOp, FChildren, FRebuild>(
+    roots: Vec<T>,
+    op_key: FOp,
+    children: FChildren,
+    rebuild: FRebuild,
+) -> (Vec<T>, usize, usize)
+where
+    T: Clone + Eq + Hash,
+    K: Eq + Hash,
+    FOp: Fn(&T) -> K,
+    FChildren: Fn(&T) -> Vec<T>,
+    FRebuild: Fn(&T, Vec<T>) -> T,
+{
+    let mut canonical: HashMap<T, T> = HashMap::new();
+    // Canonical-child-keyed cache: (op key, canonicalized children) -> the first node
+    // built with that shape.
+    let mut by_shape: HashMap<(K, Vec<T>), T> = HashMap::new();
+    let mut nodes_before = 0usize;
+
+    fn visit<T, K, FOp, FChildren, FRebuild>(
+        node: &T,
+        op_key: &FOp,
+        children_of: &FChildren,
+        rebuild: &FRebuild,
+        canonical: &mut HashMap<T, T>,
+        by_shape: &mut HashMap<(K, Vec<T>), T>,
+        nodes_before: &mut usize,
+    ) -> T
+    where
+        T: Clone + Eq + Hash,
+        K: Eq + Hash,
+        FOp: Fn(&T) -> K,
+        FChildren: Fn(&T) -> Vec<T>,
+        FRebuild: Fn(&T, Vec<T>) -> T,
+    {
+        if let Some(existing) = canonical.get(node) {
+            return existing.clone();
+        }
+        *nodes_before += 1;
+
+        let canonical_children: Vec<T> = children_of(node)
+            .iter()
+            .map(|c| visit(c, op_key, children_of, rebuild, canonical, by_shape, nodes_before))
+            .collect();
+
+        let shape = (op_key(node), canonical_children.clone());
+        let result = match by_shape.get(&shape) {
+            Some(existing) => existing.clone(),
+            None => {
+                let rebuilt = rebuild(node, canonical_children);
+                by_shape.insert(shape, rebuilt.clone());
+                rebuilt
+            }
+        };
+        canonical.insert(node.clone(), result.clone());
+        result
+    }
+
+    let new_roots: Vec<T> = roots
+        .iter()
+        .map(|r| visit(r, &op_key, &children, &rebuild, &mut canonical, &mut by_shape, &mut nodes_before))
+        .collect();
+
+    let nodes_after = by_shape.len();
+    (new_roots, nodes_before, nodes_after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    // Every node carries a `site` tag modeling separate term allocations a frontend's
+    // loop unroller would emit per iteration (distinct objects, identical shape) — so
+    // the test actually exercises structural-hash dedup rather than incidentally
+    // passing because two `Rc`s of equal value happen to already compare equal.
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    enum Expr {
+        Lit(i64, u32),
+        Add(Rc<Expr>, Rc<Expr>, u32),
+        Call(&'static str, Rc<Expr>, u32),
+    }
+
+    fn lit(v: i64, site: u32) -> Rc<Expr> {
+        Rc::new(Expr::Lit(v, site))
+    }
+    fn add(a: &Rc<Expr>, b: &Rc<Expr>, site: u32) -> Rc<Expr> {
+        Rc::new(Expr::Add(a.clone(), b.clone(), site))
+    }
+    fn call(name: &'static str, arg: &Rc<Expr>, site: u32) -> Rc<Expr> {
+        Rc::new(Expr::Call(name, arg.clone(), site))
+    }
+
+    // Shape ignores `site`: this is what makes it a structural hash rather than a
+    // per-allocation identity.
+    fn op_key(t: &Rc<Expr>) -> (&'static str, Option<i64>) {
+        match &**t {
+            Expr::Lit(v, _) => ("lit", Some(*v)),
+            Expr::Add(..) => ("add", None),
+            Expr::Call(name, ..) => (name, None),
+        }
+    }
+    fn children(t: &Rc<Expr>) -> Vec<Rc<Expr>> {
+        match &**t {
+            Expr::Lit(..) => vec![],
+            Expr::Add(a, b, _) => vec![a.clone(), b.clone()],
+            Expr::Call(_, a, _) => vec![a.clone()],
+        }
+    }
+    fn rebuild(t: &Rc<Expr>, cs: Vec<Rc<Expr>>) -> Rc<Expr> {
+        match &**t {
+            Expr::Lit(v, site) => lit(*v, *site),
+            Expr::Add(.., site) => add(&cs[0], &cs[1], *site),
+            Expr::Call(name, _, site) => call(name, &cs[0], *site),
+        }
+    }
+
+    #[test]
+    fn collapses_structurally_identical_unrolled_calls() {
+        // Three unrolled loop iterations each call the same pure helper on the same
+        // literal argument — distinct allocations (distinct `site`s), structurally
+        // identical otherwise, so they should collapse to one shared node.
+        let c1 = call("helper", &lit(7, 1), 10);
+        let c2 = call("helper", &lit(7, 2), 11);
+        let c3 = call("helper", &lit(7, 3), 12);
+        let root = add(&add(&c1, &c2, 20), &c3, 21);
+
+        let (new_roots, before, after) = dedup_dag(vec![root], op_key, children, rebuild);
+        assert_eq!(new_roots.len(), 1);
+        // 1 root add + 1 inner add + 3 calls + 3 literals = 8 nodes visited before dedup.
+        assert_eq!(before, 8);
+        // After dedup: 1 literal, 1 call, 2 adds (different children) = 4 distinct nodes.
+        assert_eq!(after, 4);
+    }
+
+    #[test]
+    fn distinct_arguments_are_not_collapsed() {
+        let c1 = call("helper", &lit(1, 1), 10);
+        let c2 = call("helper", &lit(2, 2), 11);
+        let root = add(&c1, &c2, 20);
+
+        let (_, before, after) = dedup_dag(vec![root], op_key, children, rebuild);
+        assert_eq!(before, 5);
+        // 2 distinct literals + 2 distinct calls + 1 add = 5: nothing to collapse.
+        assert_eq!(after, 5);
+    }
+}
@@ -3,6 +3,18 @@ use log::trace;
 use bincode::{deserialize_from, serialize_into};
 use serde::{de::DeserializeOwned, Serialize};
 
+mod client;
+use client::{FileClient, NetworkClient, SyncClient};
+
+mod compress;
+use compress::SpartanTranscript;
+
+mod uniform;
+
+mod hoist;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::rngs::OsRng;
+
 use circ::{
     cfg::{
         cfg,
@@ -62,6 +74,18 @@ enum Action {
 
         #[arg(long, default_value = "proof")]
         proof_path: PathBuf,
+
+        // Offload proving to a `Serve` instance at this address instead of proving
+        // in-process against `prover_key`.
+        #[arg(long, name = "HOST:PORT")]
+        server: Option<String>,
+    },
+    Serve {
+        #[arg(long, default_value = "P")]
+        prover_key: PathBuf,
+
+        #[arg(long, default_value = "127.0.0.1:7878")]
+        addr: String,
     },
     Verify {
         #[arg(long, default_value = "V")]
@@ -78,9 +102,58 @@ enum Action {
 
         #[arg(long, default_value = "proof")]
         proof_path: PathBuf,
+
+        // Verify a Groth16-compressed proof (produced by `Compress`) instead of the raw
+        // Spartan proof. Reads `transcript_path`/`groth16_vk` instead of gens/inst.
+        #[arg(long)]
+        compressed: bool,
+
+        #[arg(long, default_value = "transcript")]
+        transcript_path: PathBuf,
+
+        #[arg(long, default_value = "groth16.vk")]
+        groth16_vk: PathBuf,
+    },
+    // One-time setup for the Groth16 circuit over a transcript with `num_rounds`
+    // sumcheck rounds (the same round count `Generate` reports as "Final R1cs rounds")
+    // and `num_public_inputs` public inputs.
+    CompressSetup {
+        #[arg(long)]
+        num_rounds: usize,
+
+        #[arg(long, default_value_t = 1)]
+        num_public_inputs: usize,
+
+        #[arg(long, default_value = "groth16.pk")]
+        groth16_pk: PathBuf,
+
+        #[arg(long, default_value = "groth16.vk")]
+        groth16_vk: PathBuf,
+    },
+    Compress {
+        #[arg(long, default_value = "transcript")]
+        transcript_path: PathBuf,
+
+        #[arg(long, default_value = "groth16.pk")]
+        groth16_pk: PathBuf,
+
+        #[arg(long, default_value = "proof.compressed")]
+        compressed_proof_path: PathBuf,
     },
 }
 
+fn write_canonical<P: AsRef<Path>, T: CanonicalSerialize>(path: P, data: &T) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    data.serialize_compressed(&mut file)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+}
+
+fn read_canonical<P: AsRef<Path>, T: CanonicalDeserialize>(path: P) -> io::Result<T> {
+    let mut file = BufReader::new(File::open(path)?);
+    T::deserialize_compressed(&mut file)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+}
+
 fn write_to_path<P: AsRef<Path>, T: Serialize>(path: P, data: &T) -> io::Result<()> {
     let mut file = BufWriter::new(File::create(path)?);
     serialize_into(&mut file, &data).unwrap();
@@ -187,10 +260,28 @@ fn main() {
             let mut r1cs = to_r1cs(cs, cfg());
             println!("R1CS cons before reduce linearity {}", r1cs.constraints().len());
             println!("R1CS stats: {:#?}", r1cs.stats());
-        
+            // Detection/reporting only: `r1cs` above is already the fully materialized
+            // N-copy R1CS, so this doesn't recover the generation-RAM or proving-time
+            // savings a real uniform lowering would — that requires `to_r1cs` itself to
+            // emit a compact single-step representation, which isn't implementable from
+            // this source tree (see `uniform`'s module doc comment). This just tells you
+            // whether the step structure is there to exploit.
+            match uniform::analyze_shapes(r1cs.constraints()) {
+                Some(uniform::ShapeReport::Uniform(u)) => println!(
+                    "R1CS is uniform: {} constraints/step x {} steps ({} total)",
+                    u.num_constraints_per_step, u.num_steps, u.total_constraints
+                ),
+                Some(uniform::ShapeReport::NotUniform { distinct_shapes }) => println!(
+                    "R1CS is not uniform: {} distinct constraint shapes across {} constraints",
+                    distinct_shapes,
+                    r1cs.constraints().len()
+                ),
+                None => {}
+            }
+
             println!("Running r1cs optimizations");
             r1cs = reduce_linearities(r1cs, cfg());
-        
+
             println!("R1CS cons after reduce linearity {}", r1cs.constraints().len());
             println!("R1CS stats: {:#?}", r1cs.stats());
         
@@ -202,21 +293,61 @@ fn main() {
             spartan::write_data::<_, _>(prover_key, verifier_key, &prover_data, &verifier_data)
                 .unwrap();
         }
-        Action::Prove { prover_key, pin, gens_path, inst_path, proof_path} => {
-            let prover_input_map = parse_value_map(&std::fs::read(pin).unwrap());
-            println!("Spartan Proving");
-            let (gens, inst, proof) = spartan::prove(prover_key, &prover_input_map, options.circ.field.builtin).unwrap(); 
-            write_to_path::<_, _>(gens_path, &gens).unwrap(); // public parameters
-            write_to_path::<_, _>(inst_path, &inst) .unwrap(); // instance
-            write_to_path::<_, _>(proof_path, &proof).unwrap(); // proof
+        Action::Prove { prover_key, pin, gens_path, inst_path, proof_path, server} => {
+            let pin_bytes = std::fs::read(pin).unwrap();
+            let (gens_bytes, inst_bytes, proof_bytes) = match server {
+                Some(addr) => {
+                    println!("Spartan Proving (remote, {})", addr);
+                    NetworkClient::new(addr).unwrap().prove(&pin_bytes).unwrap()
+                }
+                None => {
+                    println!("Spartan Proving");
+                    (FileClient { prover_key }).prove(&pin_bytes).unwrap()
+                }
+            };
+            std::fs::write(gens_path, gens_bytes).unwrap(); // public parameters
+            std::fs::write(inst_path, inst_bytes).unwrap(); // instance
+            std::fs::write(proof_path, proof_bytes).unwrap(); // proof
+        }
+        Action::Serve { prover_key, addr } => {
+            client::serve(prover_key, addr).unwrap();
+        }
+        Action::Verify { verifier_key, vin, gens_path, inst_path, proof_path, compressed, transcript_path, groth16_vk } => {
+            if compressed {
+                println!("Groth16 Verifying (compressed)");
+                let transcript: SpartanTranscript = read_canonical(transcript_path).unwrap();
+                let vk = read_canonical(groth16_vk).unwrap();
+                let proof = read_canonical(proof_path).unwrap();
+                let ok = compress::verify_compressed(&vk, &transcript, &proof).unwrap();
+                assert!(ok, "Groth16 verification of the Spartan transcript failed");
+            } else {
+                let verifier_input_map = parse_value_map(&std::fs::read(vin).unwrap());
+                let gens = read_from_path::<_, _>(gens_path).unwrap();
+                let inst = read_from_path::<_, _>(inst_path).unwrap();
+                println!("Spartan Verifying");
+                let proof = read_from_path::<_, _>(proof_path).unwrap();
+                spartan::verify(verifier_key, &verifier_input_map, &gens, &inst, proof).unwrap();
+            }
         }
-        Action::Verify { verifier_key, vin, gens_path, inst_path, proof_path } => {
-            let verifier_input_map = parse_value_map(&std::fs::read(vin).unwrap());
-            println!("Spartan Verifying");
-            let gens = read_from_path::<_, _>(gens_path).unwrap();
-            let inst = read_from_path::<_, _>(inst_path).unwrap();
-            let proof = read_from_path::<_, _>(proof_path).unwrap();
-            spartan::verify(verifier_key, &verifier_input_map, &gens, &inst, proof).unwrap();
+        Action::CompressSetup { num_rounds, num_public_inputs, groth16_pk, groth16_vk } => {
+            println!(
+                "Running Groth16 circuit-specific setup ({} sumcheck rounds, {} public inputs)",
+                num_rounds, num_public_inputs
+            );
+            let (pk, vk) = compress::setup(num_rounds, num_public_inputs, compress::poseidon_config(), &mut OsRng)
+                .unwrap();
+            write_canonical(groth16_pk, &pk).unwrap();
+            write_canonical(groth16_vk, &vk).unwrap();
+        }
+        Action::Compress { transcript_path, groth16_pk, compressed_proof_path } => {
+            println!("Compressing Spartan proof to Groth16");
+            let timer = Instant::now();
+            let transcript: SpartanTranscript = read_canonical(transcript_path).unwrap();
+            let pk = read_canonical(groth16_pk).unwrap();
+            let compressed_proof =
+                compress::compress(&pk, transcript, compress::poseidon_config(), &mut OsRng).unwrap();
+            println!("compress finish {} ms\n", timer.elapsed().as_millis());
+            write_canonical(compressed_proof_path, &compressed_proof).unwrap();
         }
     }
 }
\ No newline at end of file